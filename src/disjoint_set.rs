@@ -1,36 +1,66 @@
-/// Very simple disjoint set implementation for clustering cropped textures
+use std::cell::Cell;
+
+/// Disjoint set (union-find) for clustering cropped textures.
 /// - fixed size
 /// - cannot divide the union
+/// - union by size, with iterative path compression on lookup
 pub(super) struct DisjointSet {
-    parent: Vec<usize>,
+    // `Cell` lets `root` flatten the path it walks without a `&mut` borrow,
+    // so `is_same`/`root` stay immutable and `create_cluster` is unaffected.
+    parent: Vec<Cell<usize>>,
+    size: Vec<usize>,
 }
 
-// TODO (optional): compress the path
 impl DisjointSet {
     pub fn new(num_elements: usize) -> Self {
         DisjointSet {
-            parent: (0..num_elements).collect(),
+            parent: (0..num_elements).map(Cell::new).collect(),
+            size: vec![1; num_elements],
         }
     }
 
     pub fn root(&self, x: usize) -> usize {
-        if self.parent[x] == x {
-            x
-        } else {
-            let root = self.root(self.parent[x]);
-            root
+        let mut root = x;
+        while self.parent[root].get() != root {
+            root = self.parent[root].get();
         }
+        // Point every node on the path directly at the root.
+        let mut current = x;
+        while self.parent[current].get() != root {
+            let next = self.parent[current].get();
+            self.parent[current].set(root);
+            current = next;
+        }
+        root
     }
 
     pub fn unite(&mut self, x: usize, y: usize) {
         let root_x = self.root(x);
         let root_y = self.root(y);
-        self.parent[root_x] = root_y;
+        if root_x == root_y {
+            return;
+        }
+        // Attach the smaller tree under the larger to keep it shallow.
+        let (larger, smaller) = if self.size[root_x] >= self.size[root_y] {
+            (root_x, root_y)
+        } else {
+            (root_y, root_x)
+        };
+        self.parent[smaller].set(larger);
+        self.size[larger] += self.size[smaller];
     }
 
     pub fn is_same(&self, x: usize, y: usize) -> bool {
         self.root(x) == self.root(y)
     }
+
+    /// Flatten the whole forest so every node points straight at its root.
+    pub fn compress(&mut self) {
+        for i in 0..self.parent.len() {
+            let root = self.root(i);
+            self.parent[i].set(root);
+        }
+    }
 }
 
 #[cfg(test)]
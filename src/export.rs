@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use hashbrown::HashMap;
+use image::{DynamicImage, GenericImage, RgbaImage};
+
+use crate::pack::Atlas;
+use crate::place::PlacedTextureGeometry;
+use crate::texture::cache::TextureCache;
+use crate::texture::{ChildTexture, CroppedTexture, ToplevelTexture};
+
+/// A strategy for rendering a packed atlas to an output image.
+pub trait AtlasExporter {
+    fn export(
+        &self,
+        atlas: &Atlas,
+        clusters: &HashMap<String, ToplevelTexture>,
+        children: &HashMap<String, Vec<ChildTexture>>,
+        output_path: &Path,
+        texture_cache: &TextureCache,
+        samples: u32,
+        width: u32,
+        height: u32,
+    );
+}
+
+/// Writes each atlas as a single PNG, pasting every placed toplevel texture at
+/// its location.
+#[derive(Default)]
+pub struct PngAtlasExporter {
+    // Width of the gutter to extrude into. This MUST equal the padding the
+    // `TexturePlacer` reserved, otherwise the extruded patch would not line up
+    // with the reserved region. Export only asserts that the extruded patch
+    // does not start before the atlas's top or left edge; equality of the two
+    // paddings is not checked.
+    pub padding: u32,
+    // Replicate each texture's border into the gutter to stop edge bleeding.
+    pub extrude: bool,
+}
+
+impl AtlasExporter for PngAtlasExporter {
+    fn export(
+        &self,
+        atlas: &Atlas,
+        clusters: &HashMap<String, ToplevelTexture>,
+        children: &HashMap<String, Vec<ChildTexture>>,
+        output_path: &Path,
+        texture_cache: &TextureCache,
+        samples: u32,
+        width: u32,
+        height: u32,
+    ) {
+        let mut canvas = RgbaImage::new(width, height);
+
+        for geometry in atlas {
+            let Some(toplevel) = clusters.get(&geometry.id) else {
+                continue;
+            };
+            let empty = Vec::new();
+            let cluster_children = children.get(&geometry.id).unwrap_or(&empty);
+            let patch = render_toplevel(toplevel, cluster_children, geometry, texture_cache, samples);
+            if self.extrude && self.padding > 0 {
+                // The texture sits inset by the placer's gutter; if the exporter
+                // padding is larger this would underflow or overrun the reserved
+                // region, so the two paddings must match.
+                assert!(
+                    geometry.x >= self.padding && geometry.y >= self.padding,
+                    "exporter padding ({}) exceeds the placer gutter; they must match",
+                    self.padding
+                );
+                let extruded = extrude_border(&patch, self.padding);
+                canvas
+                    .copy_from(&extruded, geometry.x - self.padding, geometry.y - self.padding)
+                    .unwrap();
+            } else {
+                canvas.copy_from(&patch, geometry.x, geometry.y).unwrap();
+            }
+        }
+
+        let output_path = output_path.with_extension("png");
+        DynamicImage::ImageRgba8(canvas).save(&output_path).unwrap();
+    }
+}
+
+/// Grow `patch` by `padding` on every side, replicating the border row/column
+/// outward so bilinear sampling and mipmaps never read a neighbour's texels.
+fn extrude_border(patch: &RgbaImage, padding: u32) -> RgbaImage {
+    let (w, h) = patch.dimensions();
+    let mut out = RgbaImage::new(w + 2 * padding, h + 2 * padding);
+    for y in 0..out.height() {
+        for x in 0..out.width() {
+            let sx = (x as i64 - padding as i64).clamp(0, w as i64 - 1) as u32;
+            let sy = (y as i64 - padding as i64).clamp(0, h as i64 - 1) as u32;
+            out.put_pixel(x, y, *patch.get_pixel(sx, sy));
+        }
+    }
+    out
+}
+
+/// Render a cluster's patch by cropping each child polygon out of the source
+/// image (applying the coverage-based alpha mask and downsample from
+/// [`CroppedTexture::crop`]) and compositing it at its offset in the toplevel.
+///
+/// The toplevel is only a bounding box, so the mask has to be applied per child
+/// here rather than on the toplevel as a whole.
+fn render_toplevel(
+    toplevel: &ToplevelTexture,
+    children: &[ChildTexture],
+    geometry: &PlacedTextureGeometry,
+    texture_cache: &TextureCache,
+    samples: u32,
+) -> RgbaImage {
+    let source = texture_cache.get(&toplevel.image_path);
+    let factor = toplevel.downsample_factor.value();
+
+    // Build the patch at its unrotated size; rotation is applied at the end so
+    // the drawn pixels match the placed UVs.
+    let (unrotated_w, unrotated_h) = if geometry.rotated {
+        (geometry.height, geometry.width)
+    } else {
+        (geometry.width, geometry.height)
+    };
+    let mut patch = RgbaImage::new(unrotated_w.max(1), unrotated_h.max(1));
+
+    for child in children {
+        // Reconstruct the child's absolute crop region inside the source image.
+        let cropped = CroppedTexture {
+            image_path: toplevel.image_path.clone(),
+            origin: (
+                toplevel.origin.0 + child.origin.0,
+                toplevel.origin.1 + child.origin.1,
+            ),
+            width: child.width,
+            height: child.height,
+            downsample_factor: toplevel.downsample_factor.clone(),
+            cropped_uv_coords: child.cropped_uv_coords.clone(),
+        };
+        let masked = cropped.crop(&source, samples).to_rgba8();
+        let ox = (child.origin.0 as f32 * factor) as i64;
+        let oy = (child.origin.1 as f32 * factor) as i64;
+        image::imageops::overlay(&mut patch, &masked, ox, oy);
+    }
+
+    if geometry.rotated {
+        image::imageops::rotate90(&patch)
+    } else {
+        patch
+    }
+}
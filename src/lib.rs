@@ -0,0 +1,5 @@
+pub mod disjoint_set;
+pub mod export;
+pub mod pack;
+pub mod place;
+pub mod texture;
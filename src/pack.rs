@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use hashbrown::HashMap;
 use rayon::prelude::*;
@@ -7,7 +7,7 @@ use crate::disjoint_set::DisjointSet;
 use crate::export::AtlasExporter;
 use crate::place::{PlacedPolygonUVCoords, PlacedTextureGeometry, TexturePlacer};
 use crate::texture::cache::TextureCache;
-use crate::texture::{ChildTexture, PolygonMappedTexture, ToplevelTexture};
+use crate::texture::{ChildTexture, DownsampleFactor, PolygonMappedTexture, ToplevelTexture};
 pub type Atlas = Vec<PlacedTextureGeometry>;
 
 pub struct AtlasPacker {
@@ -30,6 +30,34 @@ pub(super) struct Cluster {
     pub children: Vec<(String, ChildTexture)>,
 }
 
+/// What to do with a cluster whose placed size exceeds the atlas dimensions.
+#[derive(Debug, Clone)]
+pub enum OversizePolicy {
+    // Apply an extra downsample factor to the cluster so it fits the atlas.
+    Downsample,
+    // Give the cluster its own atlas and cap its placed dimension at
+    // `max_texture_dim`, downsampling it to fit. The atlas itself is still the
+    // placer's width/height; this only bounds the cluster so it stays within a
+    // hardware texture-size limit.
+    DedicatedAtlas { max_texture_dim: u32 },
+}
+
+/// Scale a toplevel's downsample factor so its placed size fits `target`,
+/// leaving it untouched if it already fits. The children's UV coordinates are
+/// relative to the bounding box, so an extra uniform downsample preserves them.
+fn fit_within(toplevel: &ToplevelTexture, target: (u32, u32)) -> ToplevelTexture {
+    let (tw, th) = toplevel.downsampled_size();
+    // A degenerate cluster with a zero-size placement already fits and would
+    // make `target / 0` non-finite below, which `DownsampleFactor::new` rejects.
+    if (tw <= target.0 && th <= target.1) || tw == 0 || th == 0 {
+        return toplevel.clone();
+    }
+    let fit = (target.0 as f32 / tw as f32).min(target.1 as f32 / th as f32);
+    let mut fitted = toplevel.clone();
+    fitted.downsample_factor = DownsampleFactor::new(&(toplevel.downsample_factor.value() * fit));
+    fitted
+}
+
 impl AtlasPacker {
     pub fn add_texture(&mut self, texture_id: String, texture: PolygonMappedTexture) {
         self.textures.insert(texture_id, texture);
@@ -38,16 +66,35 @@ impl AtlasPacker {
     fn create_cluster(&self) -> HashMap<String, Cluster> {
         let texture_ids: Vec<String> = self.textures.keys().cloned().collect();
 
+        // Hash each texture's bbox into the cells of a uniform spatial grid,
+        // bucketed per source image (only textures from the same image can
+        // overlap). Overlap tests then happen only between textures sharing a
+        // cell, turning the old O(n^2) pairwise scan into roughly linear work.
         let disjoint_set = {
             let mut disjoint_set = DisjointSet::new(texture_ids.len());
 
+            const CELL_SIZE: u32 = 256;
+            let mut grid: HashMap<(PathBuf, u32, u32), Vec<usize>> = HashMap::new();
+
             for i in 0..texture_ids.len() {
-                for j in (i + 1)..texture_ids.len() {
-                    let texture_i = self.textures.get(&texture_ids[i]).unwrap();
-                    let texture_j = self.textures.get(&texture_ids[j]).unwrap();
+                let texture = self.textures.get(&texture_ids[i]).unwrap();
+                let (x, y) = texture.cropped.origin;
+                let (w, h) = (texture.cropped.width, texture.cropped.height);
+                let image_path = texture.image_path().to_path_buf();
 
-                    if texture_i.bbox_overlaps(texture_j) {
-                        disjoint_set.unite(i, j);
+                for cx in (x / CELL_SIZE)..=((x + w) / CELL_SIZE) {
+                    for cy in (y / CELL_SIZE)..=((y + h) / CELL_SIZE) {
+                        let bucket = grid.entry((image_path.clone(), cx, cy)).or_default();
+                        for &j in bucket.iter() {
+                            if disjoint_set.is_same(i, j) {
+                                continue;
+                            }
+                            let other = self.textures.get(&texture_ids[j]).unwrap();
+                            if texture.bbox_overlaps(other) {
+                                disjoint_set.unite(i, j);
+                            }
+                        }
+                        bucket.push(i);
                     }
                 }
             }
@@ -102,25 +149,65 @@ impl AtlasPacker {
         cluster_map
     }
 
-    pub fn pack<P: TexturePlacer>(self, mut placer: P) -> PackedAtlasProvider {
+    pub fn pack<P: TexturePlacer>(
+        self,
+        mut placer: P,
+        oversize_policy: OversizePolicy,
+    ) -> PackedAtlasProvider {
         let mut current_atlas: Atlas = Vec::new();
         let mut atlases: HashMap<String, Atlas> = HashMap::new();
+        let (atlas_width, atlas_height) = placer.atlas_size();
+        // The placer reserves a gutter on every side, so the usable area a
+        // cluster must fit into is the atlas minus twice the padding.
+        let gutter = 2 * placer.padding();
+        let usable = (
+            atlas_width.saturating_sub(gutter),
+            atlas_height.saturating_sub(gutter),
+        );
 
-        let clusters = self.create_cluster();
+        let mut clusters = self.create_cluster();
+        let cluster_ids: Vec<String> = clusters.keys().cloned().collect();
         let mut texture_info_map: HashMap<String, PlacedPolygonUVCoords> = HashMap::new();
-        for (cluster_id, cluster) in clusters.iter() {
-            if !placer.can_place(&cluster.toplevel_texture) {
-                let current_atlas_id = atlases.len();
-                atlases.insert(current_atlas_id.to_string(), current_atlas.clone());
-                current_atlas.clear();
+        for cluster_id in &cluster_ids {
+            let (original_toplevel, children) = {
+                let cluster = clusters.get(cluster_id).unwrap();
+                (cluster.toplevel_texture.clone(), cluster.children.clone())
+            };
+
+            // A cluster larger than the atlas can never be placed as-is; apply
+            // the configured oversize policy so the output stays valid.
+            let (toplevel_texture, dedicated) = match &oversize_policy {
+                OversizePolicy::Downsample => (fit_within(&original_toplevel, usable), false),
+                OversizePolicy::DedicatedAtlas { max_texture_dim } => {
+                    let (tw, th) = original_toplevel.downsampled_size();
+                    if tw > usable.0 || th > usable.1 {
+                        let cap = (
+                            usable.0.min(max_texture_dim.saturating_sub(gutter)),
+                            usable.1.min(max_texture_dim.saturating_sub(gutter)),
+                        );
+                        (fit_within(&original_toplevel, cap), true)
+                    } else {
+                        (original_toplevel, false)
+                    }
+                }
+            };
+
+            // An oversize cluster routed to its own atlas flushes whatever is
+            // already in flight first, so it never shares space with others.
+            if dedicated || !placer.can_place(&toplevel_texture) {
+                if !current_atlas.is_empty() {
+                    let current_atlas_id = atlases.len();
+                    atlases.insert(current_atlas_id.to_string(), current_atlas.clone());
+                    current_atlas.clear();
+                }
                 placer.reset_param();
             }
 
             let current_atlas_id = atlases.len().to_string();
 
             let (toplevel_texture_info, children_texture_infos) = placer.place_texture(
-                cluster.toplevel_texture.clone(),
-                cluster.children.clone(),
+                toplevel_texture.clone(),
+                children.clone(),
                 cluster_id.clone(),
                 current_atlas_id,
             );
@@ -129,12 +216,26 @@ impl AtlasPacker {
 
             for (child_texture_info, child_texture_id) in children_texture_infos
                 .iter()
-                .zip(cluster.children.iter().map(|(id, _)| id))
+                .zip(children.iter().map(|(id, _)| id))
             {
                 if let Some(child_texture_info) = child_texture_info {
                     texture_info_map.insert(child_texture_id.clone(), child_texture_info.clone());
                 }
             }
+
+            // Record the toplevel actually placed (with its fitted downsample
+            // factor) so export renders children at the same scale as the
+            // placed geometry and UVs.
+            clusters.get_mut(cluster_id).unwrap().toplevel_texture = toplevel_texture;
+
+            // Seal the dedicated atlas immediately so the next cluster starts
+            // from a clean atlas.
+            if dedicated {
+                let current_atlas_id = atlases.len();
+                atlases.insert(current_atlas_id.to_string(), current_atlas.clone());
+                current_atlas.clear();
+                placer.reset_param();
+            }
         }
 
         // treat the last atlas
@@ -168,20 +269,35 @@ impl PackedAtlasProvider {
         exporter: E,
         output_dir: &Path,
         texture_cache: &TextureCache,
+        samples: u32,
         width: u32,
         height: u32,
     ) {
+        let toplevels = self
+            .clusters
+            .iter()
+            .map(|(id, cluster)| (id.clone(), cluster.toplevel_texture.clone()))
+            .collect::<HashMap<String, ToplevelTexture>>();
+        let children = self
+            .clusters
+            .iter()
+            .map(|(id, cluster)| {
+                (
+                    id.clone(),
+                    cluster.children.iter().map(|(_, child)| child.clone()).collect(),
+                )
+            })
+            .collect::<HashMap<String, Vec<ChildTexture>>>();
+
         self.atlases.par_iter().for_each(|(id, atlas)| {
             let output_path = output_dir.join(id);
             exporter.export(
                 atlas,
-                &self
-                    .clusters
-                    .iter()
-                    .map(|(id, cluster)| (id.clone(), cluster.toplevel_texture.clone()))
-                    .collect::<HashMap<String, ToplevelTexture>>(),
+                &toplevels,
+                &children,
                 &output_path,
                 texture_cache,
+                samples,
                 width,
                 height,
             );
@@ -191,4 +307,100 @@ impl PackedAtlasProvider {
     pub fn get_texture_info(&self, id: &str) -> Option<&PlacedPolygonUVCoords> {
         self.texture_info_map.get(id)
     }
+
+    /// Compute per-atlas and aggregate packing-efficiency statistics.
+    ///
+    /// For each atlas the used area is the sum of its placed rects' areas; the
+    /// utilization is that divided by `width * height`. Use this to tune the
+    /// downsample factor, padding, and atlas size by measuring waste instead of
+    /// guessing.
+    pub fn stats(&self, width: u32, height: u32) -> AtlasStats {
+        let atlas_area = width as u64 * height as u64;
+
+        let mut per_atlas: HashMap<String, f64> = HashMap::new();
+        let mut used_area = 0u64;
+        for (id, atlas) in self.atlases.iter() {
+            let atlas_used: u64 = atlas
+                .iter()
+                .map(|geometry| geometry.width as u64 * geometry.height as u64)
+                .sum();
+            used_area += atlas_used;
+            per_atlas.insert(id.clone(), atlas_used as f64 / atlas_area as f64);
+        }
+
+        let atlas_count = self.atlases.len();
+        let total_area = atlas_area * atlas_count as u64;
+
+        AtlasStats {
+            per_atlas,
+            atlas_count,
+            used_area,
+            total_area,
+            wasted_area: total_area - used_area,
+            utilization: if total_area == 0 {
+                0.0
+            } else {
+                used_area as f64 / total_area as f64
+            },
+        }
+    }
+}
+
+/// Packing-efficiency statistics for a set of atlases.
+#[derive(Debug, Clone)]
+pub struct AtlasStats {
+    // atlas id -> fill ratio (used area / atlas area)
+    pub per_atlas: HashMap<String, f64>,
+    pub atlas_count: usize,
+    pub used_area: u64,
+    pub total_area: u64,
+    pub wasted_area: u64,
+    // Aggregate fill ratio across every atlas.
+    pub utilization: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::place::MaxRectsPlacer;
+    use crate::texture::{CroppedTexture, DownsampleFactor};
+    use std::path::PathBuf;
+
+    fn polygon_mapped(origin: (u32, u32), size: (u32, u32)) -> PolygonMappedTexture {
+        PolygonMappedTexture::new(CroppedTexture {
+            image_path: PathBuf::from("x.png"),
+            origin,
+            width: size.0,
+            height: size.1,
+            downsample_factor: DownsampleFactor::new(&1.0),
+            cropped_uv_coords: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+        })
+    }
+
+    #[test]
+    fn oversize_cluster_geometry_matches_stored_toplevel() {
+        // One texture far larger than the 64x64 atlas.
+        let mut packer = AtlasPacker::default();
+        packer.add_texture("big".to_string(), polygon_mapped((0, 0), (200, 50)));
+
+        let provider = packer.pack(MaxRectsPlacer::new(64, 64, 0), OversizePolicy::Downsample);
+
+        let geometry = provider
+            .atlases
+            .values()
+            .flat_map(|atlas| atlas.iter())
+            .next()
+            .expect("the oversize cluster should still be placed");
+        let toplevel = &provider.clusters[&geometry.id].toplevel_texture;
+
+        // The stored toplevel carries the fitted downsample factor, so the scale
+        // export renders at matches the placed geometry.
+        let placed = if geometry.rotated {
+            (geometry.height, geometry.width)
+        } else {
+            (geometry.width, geometry.height)
+        };
+        assert_eq!(placed, toplevel.downsampled_size());
+        assert!(geometry.width <= 64 && geometry.height <= 64);
+    }
 }
@@ -0,0 +1,376 @@
+use crate::texture::{ChildTexture, ToplevelTexture};
+
+// An axis-aligned rectangle in atlas pixel space (top-left origin).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    /// Whether this rectangle overlaps `other` (touching edges do not count).
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    /// Whether this rectangle is fully contained in `other`.
+    fn contained_in(&self, other: &Rect) -> bool {
+        self.x >= other.x
+            && self.y >= other.y
+            && self.right() <= other.right()
+            && self.bottom() <= other.bottom()
+    }
+}
+
+// The placement of a toplevel texture's bounding box inside an atlas.
+#[derive(Debug, Clone)]
+pub struct PlacedTextureGeometry {
+    pub id: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Whether the toplevel was rotated 90° to improve the fit.
+    pub rotated: bool,
+}
+
+// The UV coordinates of a child polygon after it has been placed into an atlas.
+#[derive(Debug, Clone)]
+pub struct PlacedPolygonUVCoords {
+    pub atlas_id: String,
+    pub uv_coords: Vec<(f64, f64)>,
+}
+
+/// A strategy for placing toplevel textures into fixed-size atlases.
+pub trait TexturePlacer {
+    /// The atlas dimensions this placer targets.
+    fn atlas_size(&self) -> (u32, u32);
+
+    /// The padding gutter reserved around each placed texture.
+    fn padding(&self) -> u32 {
+        0
+    }
+
+    /// Whether `texture` still fits in the current atlas.
+    fn can_place(&self, texture: &ToplevelTexture) -> bool;
+
+    /// Place `toplevel` and map each child polygon's UVs into atlas space.
+    fn place_texture(
+        &mut self,
+        toplevel: ToplevelTexture,
+        children: Vec<(String, ChildTexture)>,
+        cluster_id: String,
+        atlas_id: String,
+    ) -> (PlacedTextureGeometry, Vec<Option<PlacedPolygonUVCoords>>);
+
+    /// Reset per-atlas state so the next placement starts from an empty atlas.
+    fn reset_param(&mut self);
+}
+
+/// Map a child polygon's `cropped_uv_coords` into the atlas, given where the
+/// enclosing toplevel landed. UVs use a bottom-left origin, the atlas uses a
+/// top-left origin, so the vertical axis is flipped on the way in and out.
+/// When the toplevel is rotated 90° the child UVs are rotated the same way: the
+/// v-flip composed with the clockwise `rotate90` the exporter applies sends a
+/// normalized top-left point `(nx, ny)` to `(1 - ny, nx)`.
+fn map_child_uv(
+    child: &ChildTexture,
+    placed: &Rect,
+    toplevel: (u32, u32),
+    rotated: bool,
+    atlas_width: u32,
+    atlas_height: u32,
+) -> Vec<(f64, f64)> {
+    let (tw, th) = (toplevel.0 as f64, toplevel.1 as f64);
+    let (ox, oy) = (child.origin.0 as f64, child.origin.1 as f64);
+    let (cw, ch) = (child.width as f64, child.height as f64);
+    child
+        .cropped_uv_coords
+        .iter()
+        .map(|(u, v)| {
+            // Point within the unrotated toplevel, normalized, top-left origin.
+            let nx = (ox + u * cw) / tw;
+            let ny = (oy + (1.0 - v) * ch) / th;
+            // Rotate the unit square to match the rotated placement. The export
+            // uses `rotate90` (clockwise), which sends a top-left point
+            // `(nx, ny)` to `(1 - ny, nx)`; the UVs must follow the same way.
+            let (rx, ry) = if rotated { (1.0 - ny, nx) } else { (nx, ny) };
+            let px = placed.x as f64 + rx * placed.width as f64;
+            let py = placed.y as f64 + ry * placed.height as f64;
+            (px / atlas_width as f64, 1.0 - py / atlas_height as f64)
+        })
+        .collect()
+}
+
+/// A Best-Short-Side-Fit MaxRects bin packer.
+///
+/// The atlas is tracked as a list of maximal free rectangles. Each placement
+/// picks the free rectangle that leaves the smallest leftover, carves the
+/// placed rectangle out of every free rectangle it touches, and prunes any
+/// free rectangle that another already contains. This packs the heterogeneous
+/// cluster sizes this crate produces far more densely than shelf packing.
+pub struct MaxRectsPlacer {
+    width: u32,
+    height: u32,
+    // Gutter reserved around every placed texture to stop bilinear/mipmap bleed.
+    padding: u32,
+    free_rects: Vec<Rect>,
+}
+
+impl MaxRectsPlacer {
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding,
+            free_rects: vec![Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }],
+        }
+    }
+
+    /// Find the free rectangle giving the best (smallest) short-side fit for a
+    /// rectangle of size `(w, h)`, returning its top-left placement and whether
+    /// the rectangle had to be rotated 90° to achieve that fit.
+    /// The returned rectangle is the *reserved* region including the padding
+    /// gutter on all sides; inset it by `padding` to get the texture's pixels.
+    fn find_position(&self, w: u32, h: u32) -> Option<(Rect, bool)> {
+        let pad = 2 * self.padding;
+        let mut best: Option<((Rect, bool), (u32, u32))> = None;
+        for free in &self.free_rects {
+            for (rw, rh, rotated) in [(w + pad, h + pad, false), (h + pad, w + pad, true)] {
+                if free.width < rw || free.height < rh {
+                    continue;
+                }
+                let leftover_x = free.width - rw;
+                let leftover_y = free.height - rh;
+                let score = (leftover_x.min(leftover_y), leftover_x.max(leftover_y));
+                if best.as_ref().map_or(true, |(_, b)| score < *b) {
+                    best = Some((
+                        (
+                            Rect {
+                                x: free.x,
+                                y: free.y,
+                                width: rw,
+                                height: rh,
+                            },
+                            rotated,
+                        ),
+                        score,
+                    ));
+                }
+            }
+        }
+        best.map(|(placement, _)| placement)
+    }
+
+    /// Carve `placed` out of every intersecting free rectangle and prune.
+    fn insert(&mut self, placed: &Rect) {
+        let mut residual = Vec::new();
+        self.free_rects.retain(|free| {
+            if !free.intersects(placed) {
+                return true;
+            }
+            // Left strip.
+            if placed.x > free.x {
+                residual.push(Rect {
+                    x: free.x,
+                    y: free.y,
+                    width: placed.x - free.x,
+                    height: free.height,
+                });
+            }
+            // Right strip.
+            if placed.right() < free.right() {
+                residual.push(Rect {
+                    x: placed.right(),
+                    y: free.y,
+                    width: free.right() - placed.right(),
+                    height: free.height,
+                });
+            }
+            // Top strip.
+            if placed.y > free.y {
+                residual.push(Rect {
+                    x: free.x,
+                    y: free.y,
+                    width: free.width,
+                    height: placed.y - free.y,
+                });
+            }
+            // Bottom strip.
+            if placed.bottom() < free.bottom() {
+                residual.push(Rect {
+                    x: free.x,
+                    y: placed.bottom(),
+                    width: free.width,
+                    height: free.bottom() - placed.bottom(),
+                });
+            }
+            false
+        });
+        self.free_rects.append(&mut residual);
+        self.prune();
+    }
+
+    /// Drop any free rectangle fully contained in another.
+    fn prune(&mut self) {
+        let mut i = 0;
+        while i < self.free_rects.len() {
+            let mut removed = false;
+            let mut j = i + 1;
+            while j < self.free_rects.len() {
+                if self.free_rects[i].contained_in(&self.free_rects[j]) {
+                    self.free_rects.swap_remove(i);
+                    removed = true;
+                    break;
+                }
+                if self.free_rects[j].contained_in(&self.free_rects[i]) {
+                    self.free_rects.swap_remove(j);
+                } else {
+                    j += 1;
+                }
+            }
+            if !removed {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl TexturePlacer for MaxRectsPlacer {
+    fn atlas_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    fn can_place(&self, texture: &ToplevelTexture) -> bool {
+        let (w, h) = texture.downsampled_size();
+        self.find_position(w, h).is_some()
+    }
+
+    fn place_texture(
+        &mut self,
+        toplevel: ToplevelTexture,
+        children: Vec<(String, ChildTexture)>,
+        cluster_id: String,
+        atlas_id: String,
+    ) -> (PlacedTextureGeometry, Vec<Option<PlacedPolygonUVCoords>>) {
+        let (w, h) = toplevel.downsampled_size();
+        let (reserved, rotated) = self
+            .find_position(w, h)
+            .expect("place_texture called without a preceding can_place check");
+        self.insert(&reserved);
+
+        // The texture itself occupies the reserved region inset by the gutter;
+        // exported UVs address this inner rect, not the padded one.
+        let placed = Rect {
+            x: reserved.x + self.padding,
+            y: reserved.y + self.padding,
+            width: reserved.width - 2 * self.padding,
+            height: reserved.height - 2 * self.padding,
+        };
+
+        // Child offsets/sizes are in the toplevel's full-resolution pixel space;
+        // the downsample factor cancels in the normalized ratios below.
+        let full_size = (toplevel.width, toplevel.height);
+        let uv_coords = children
+            .iter()
+            .map(|(_, child)| {
+                Some(PlacedPolygonUVCoords {
+                    atlas_id: atlas_id.clone(),
+                    uv_coords: map_child_uv(
+                        child,
+                        &placed,
+                        full_size,
+                        rotated,
+                        self.width,
+                        self.height,
+                    ),
+                })
+            })
+            .collect();
+
+        let geometry = PlacedTextureGeometry {
+            id: cluster_id,
+            x: placed.x,
+            y: placed.y,
+            width: placed.width,
+            height: placed.height,
+            rotated,
+        };
+        (geometry, uv_coords)
+    }
+
+    fn reset_param(&mut self) {
+        self.free_rects = vec![Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        }];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::DownsampleFactor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn rotated_child_uv_matches_clockwise_export() {
+        // An atlas wider than it is tall forces a 15x80 toplevel to rotate to
+        // 80x15 to fit at all, so the placer selects the rotated orientation.
+        let mut placer = MaxRectsPlacer::new(100, 20, 0);
+        let toplevel = ToplevelTexture {
+            image_path: PathBuf::from("x.png"),
+            origin: (0, 0),
+            width: 15,
+            height: 80,
+            downsample_factor: DownsampleFactor::new(&1.0),
+        };
+        assert!(placer.can_place(&toplevel));
+
+        // A child covering the whole toplevel, with its top-left corner (u=0,
+        // v=1) as the only probed vertex.
+        let child = ChildTexture {
+            origin: (0, 0),
+            width: 15,
+            height: 80,
+            cropped_uv_coords: vec![(0.0, 1.0)],
+        };
+        let (geometry, uvs) = placer.place_texture(
+            toplevel,
+            vec![("child".to_string(), child)],
+            "cluster".to_string(),
+            "0".to_string(),
+        );
+
+        assert!(geometry.rotated);
+        // `rotate90` draws the toplevel top-left at the placed rect's top-right,
+        // i.e. pixel (80, 0) in a 100x20 atlas => UV (0.8, 1.0).
+        let uv = uvs[0].as_ref().unwrap().uv_coords[0];
+        assert!((uv.0 - 0.8).abs() < 1e-9, "u = {}", uv.0);
+        assert!((uv.1 - 1.0).abs() < 1e-9, "v = {}", uv.1);
+    }
+}
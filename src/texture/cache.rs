@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use hashbrown::HashMap;
+use image::DynamicImage;
+
+use super::utils::get_image_size;
+
+/// A process-wide cache of decoded source images, keyed by file path.
+///
+/// Decoding the same atlas source image once per cropped texture is wasteful,
+/// so the first load is memoized and subsequent crops reuse the decoded buffer.
+#[derive(Default)]
+pub struct TextureCache {
+    images: Mutex<HashMap<PathBuf, DynamicImage>>,
+}
+
+impl TextureCache {
+    pub fn new() -> Self {
+        Self {
+            images: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return the decoded image for `path`, loading and caching it on first use.
+    pub fn get(&self, path: &Path) -> DynamicImage {
+        let mut images = self.images.lock().unwrap();
+        if let Some(image) = images.get(path) {
+            return image.clone();
+        }
+        let image = image::open(path).unwrap();
+        images.insert(path.to_path_buf(), image.clone());
+        image
+    }
+
+    /// Return the dimensions of the source image without forcing a full decode.
+    pub fn size(&self, path: &Path) -> (u32, u32) {
+        get_image_size(path).unwrap()
+    }
+}
@@ -102,14 +102,19 @@ impl CroppedTexture {
         }
     }
 
-    pub fn crop(&self, image: &DynamicImage) -> DynamicImage {
+    /// Crop the texture to its polygon, anti-aliasing the polygon boundary.
+    ///
+    /// Each output pixel is probed at the centers of a `samples` × `samples`
+    /// sub-pixel grid; its alpha is scaled by the fraction of sub-samples that
+    /// fall inside the polygon, so fully-outside pixels become transparent,
+    /// fully-inside pixels keep their alpha, and edge pixels get a smooth ramp.
+    pub fn crop(&self, image: &DynamicImage, samples: u32) -> DynamicImage {
         let (x, y) = self.origin;
         let cropped_image = image.view(x, y, self.width, self.height).to_image();
 
         // Collect pixels into a Vec and then process in parallel
         let pixels: Vec<_> = cropped_image.enumerate_pixels().collect();
 
-        let samples = 1;
         let num_threads = rayon::current_num_threads();
         let chunk_size = (pixels.len() / num_threads).clamp(1, pixels.len() + 1);
 
@@ -122,35 +127,28 @@ impl CroppedTexture {
                 let mut local_results = Vec::new();
 
                 for &(px, py, pixel) in chunk {
-                    let mut is_inside = false;
+                    let mut inside_count = 0u32;
 
-                    'subpixels: for sx in 0..samples {
+                    for sx in 0..samples {
                         for sy in 0..samples {
                             let x = (px as f64 + (sx as f64 + 0.5) / samples as f64)
                                 / self.width as f64;
                             let y = 1.0
                                 - (py as f64 + (sy as f64 + 0.5) / samples as f64)
                                     / self.height as f64;
-                            // Adjust x and y to the center of the pixel
-                            let center_x = x + 0.5 / self.width as f64;
-                            let center_y = y - 0.5 / self.height as f64;
-
-                            if is_point_inside_polygon(
-                                (center_x, center_y),
-                                &self.cropped_uv_coords,
-                            ) {
-                                is_inside = true;
-                                break 'subpixels;
+
+                            if is_point_inside_polygon((x, y), &self.cropped_uv_coords) {
+                                inside_count += 1;
                             }
                         }
                     }
 
-                    if is_inside {
-                        local_results.push((px, py, *pixel));
-                    } else {
-                        // FIXME: Do not crop temporarily because pixel boundary jaggies will occur.
-                        local_results.push((px, py, *pixel));
-                    }
+                    // Scale the pixel's alpha by its fractional polygon coverage;
+                    // out-of-polygon texels end up fully transparent.
+                    let coverage = inside_count as f32 / (samples * samples) as f32;
+                    let mut pixel = *pixel;
+                    pixel.0[3] = (pixel.0[3] as f32 * coverage).round() as u8;
+                    local_results.push((px, py, pixel));
                 }
 
                 s.send(local_results).unwrap();
@@ -199,3 +197,115 @@ fn is_point_inside_polygon(test_point: (f64, f64), polygon: &[(f64, f64)]) -> bo
 
     is_inside
 }
+
+// A single polygon-mapped texture: a cropped region of a source image together
+// with the polygon UV coordinates that select the visible part of it.
+#[derive(Debug, Clone)]
+pub struct PolygonMappedTexture {
+    pub cropped: CroppedTexture,
+}
+
+impl PolygonMappedTexture {
+    pub fn new(cropped: CroppedTexture) -> Self {
+        Self { cropped }
+    }
+
+    pub fn image_path(&self) -> &Path {
+        &self.cropped.image_path
+    }
+
+    /// Check if this texture's bounding box overlaps the other's.
+    ///
+    /// Only textures cut from the same source image can overlap, mirroring the
+    /// `image_path` check in [`CroppedTexture::overlaps`].
+    pub fn bbox_overlaps(&self, other: &Self) -> bool {
+        self.cropped.overlaps(&other.cropped)
+    }
+
+    fn bbox(&self) -> (u32, u32, u32, u32) {
+        (
+            self.cropped.origin.0,
+            self.cropped.origin.1,
+            self.cropped.width,
+            self.cropped.height,
+        )
+    }
+}
+
+// The bounding texture of a cluster of overlapping polygon-mapped textures.
+// All children share the same source image; the toplevel is the union of their
+// bounding boxes and is what actually gets placed into an atlas.
+#[derive(Debug, Clone)]
+pub struct ToplevelTexture {
+    pub image_path: PathBuf,
+    pub origin: (u32, u32),
+    pub width: u32,
+    pub height: u32,
+    pub downsample_factor: DownsampleFactor,
+}
+
+impl ToplevelTexture {
+    pub fn new(texture: &PolygonMappedTexture) -> Self {
+        let (x, y, w, h) = texture.bbox();
+        Self {
+            image_path: texture.cropped.image_path.clone(),
+            origin: (x, y),
+            width: w,
+            height: h,
+            downsample_factor: texture.cropped.downsample_factor.clone(),
+        }
+    }
+
+    /// Expand the bounding box to also contain `texture`.
+    ///
+    /// Returns `None` if the texture comes from a different source image and so
+    /// cannot share a toplevel.
+    pub fn expand(&self, texture: &PolygonMappedTexture) -> Option<Self> {
+        if self.image_path != texture.cropped.image_path {
+            return None;
+        }
+        let (x, y, w, h) = texture.bbox();
+        let min_x = self.origin.0.min(x);
+        let min_y = self.origin.1.min(y);
+        let max_x = (self.origin.0 + self.width).max(x + w);
+        let max_y = (self.origin.1 + self.height).max(y + h);
+        Some(Self {
+            image_path: self.image_path.clone(),
+            origin: (min_x, min_y),
+            width: max_x - min_x,
+            height: max_y - min_y,
+            downsample_factor: self.downsample_factor.clone(),
+        })
+    }
+
+    /// The placed size of the toplevel after its downsample factor is applied.
+    pub fn downsampled_size(&self) -> (u32, u32) {
+        let factor = self.downsample_factor.value();
+        (
+            (self.width as f32 * factor) as u32,
+            (self.height as f32 * factor) as u32,
+        )
+    }
+
+    /// Describe how `texture` sits inside this toplevel (its pixel offset and UVs).
+    pub fn get_child(&self, texture: &PolygonMappedTexture) -> ChildTexture {
+        let (x, y, w, h) = texture.bbox();
+        ChildTexture {
+            origin: (x - self.origin.0, y - self.origin.1),
+            width: w,
+            height: h,
+            cropped_uv_coords: texture.cropped.cropped_uv_coords.clone(),
+        }
+    }
+}
+
+// A child texture expressed relative to its enclosing [`ToplevelTexture`].
+#[derive(Debug, Clone)]
+pub struct ChildTexture {
+    // Offset of the child inside the toplevel, in toplevel pixel space.
+    pub origin: (u32, u32),
+    pub width: u32,
+    pub height: u32,
+    // UV coordinates of the child polygon (bottom-left origin).
+    pub cropped_uv_coords: Vec<(f64, f64)>,
+}